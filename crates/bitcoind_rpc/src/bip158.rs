@@ -8,51 +8,256 @@
 
 use bdk_core::bitcoin;
 use bdk_core::{BlockId, CheckPoint};
-use bitcoin::{bip158::BlockFilter, Block, ScriptBuf};
+use bitcoin::hashes::Hash as _;
+use bitcoin::{bip158::BlockFilter, Block, BlockHash, ScriptBuf};
 use bitcoincore_rpc;
-use bitcoincore_rpc::{json::GetBlockHeaderResult, RpcApi};
+use bitcoincore_rpc::{
+    json::{GetBlockFilterResult, GetBlockHeaderResult},
+    RpcApi,
+};
+use miniscript::{Descriptor, DescriptorPublicKey};
+
+/// A BIP157 filter header, committing to a [`BlockFilter`] and to the filter header of the
+/// previous block.
+pub type FilterHeader = bitcoin::hashes::sha256d::Hash;
+
+/// The block/filter data operations [`FilterIter`] needs from its backing data source.
+///
+/// This is implemented for [`bitcoincore_rpc::Client`] so existing callers are unaffected, but
+/// downstream crates can implement it for other transports (an Electrum-backed provider, a
+/// BIP157 P2P client, an in-memory cache for tests, etc.) to reuse `FilterIter`'s scanning and
+/// reorg-handling logic without depending on Core RPC.
+pub trait FilterProvider {
+    /// Error type returned by the provider.
+    type Error: Into<Error>;
+
+    /// Get the hash of the block at `height`.
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash, Self::Error>;
+
+    /// Get header info for the block identified by `hash`.
+    fn get_block_header_info(&self, hash: &BlockHash) -> Result<GetBlockHeaderResult, Self::Error>;
+
+    /// Get the BIP158 compact block filter for the block identified by `hash`.
+    fn get_block_filter(&self, hash: &BlockHash) -> Result<GetBlockFilterResult, Self::Error>;
+
+    /// Get the BIP157 basic filter header committing to the block identified by `hash`.
+    fn get_block_filter_header(&self, hash: &BlockHash) -> Result<FilterHeader, Self::Error>;
+
+    /// Get the full block identified by `hash`.
+    fn get_block(&self, hash: &BlockHash) -> Result<Block, Self::Error>;
+}
+
+impl FilterProvider for bitcoincore_rpc::Client {
+    // `Error` already has a `From<bitcoincore_rpc::Error>` impl, so using it directly here
+    // (rather than `bitcoincore_rpc::Error`) lets `get_block_filter_header` report a malformed
+    // response as a proper `Error` instead of having to panic.
+    type Error = Error;
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash, Self::Error> {
+        Ok(RpcApi::get_block_hash(self, height)?)
+    }
+
+    fn get_block_header_info(&self, hash: &BlockHash) -> Result<GetBlockHeaderResult, Self::Error> {
+        Ok(RpcApi::get_block_header_info(self, hash)?)
+    }
+
+    fn get_block_filter(&self, hash: &BlockHash) -> Result<GetBlockFilterResult, Self::Error> {
+        Ok(RpcApi::get_block_filter(self, hash)?)
+    }
+
+    fn get_block_filter_header(&self, hash: &BlockHash) -> Result<FilterHeader, Self::Error> {
+        let header_hex: String = RpcApi::call(
+            self,
+            "getblockfilterheader",
+            &[serde_json::json!(hash), serde_json::json!("basic")],
+        )?;
+        header_hex
+            .parse()
+            .map_err(|_| Error::InvalidFilterHeader)
+    }
+
+    fn get_block(&self, hash: &BlockHash) -> Result<Block, Self::Error> {
+        Ok(RpcApi::get_block(self, hash)?)
+    }
+}
+
+/// The script pubkey inventory scanned by [`FilterIter`].
+///
+/// This is either a fixed, caller-provided set of SPKs, or an inventory derived on demand from a
+/// descriptor, kept `gap_limit` indices ahead of the highest derivation index seen in a matching
+/// block (following the BIP84 descriptor-wallet scanning pattern used by Electrum-backed BDK
+/// wallets).
+#[derive(Debug)]
+enum SpkInventory {
+    /// A fixed set of SPKs, unrelated to any descriptor.
+    Static(Vec<ScriptBuf>),
+    /// SPKs derived from `descriptor`, kept `gap_limit` indices ahead of `last_used`.
+    Descriptor {
+        descriptor: Descriptor<DescriptorPublicKey>,
+        gap_limit: u32,
+        /// Derived `(index, spk)` pairs, in derivation order starting at index 0.
+        spks: Vec<(u32, ScriptBuf)>,
+        /// Highest derivation index seen in a matching block so far, if any.
+        last_used: Option<u32>,
+    },
+}
+
+impl SpkInventory {
+    /// All SPKs currently in the inventory.
+    fn scripts(&self) -> Box<dyn Iterator<Item = &ScriptBuf> + '_> {
+        match self {
+            Self::Static(spks) => Box::new(spks.iter()),
+            Self::Descriptor { spks, .. } => Box::new(spks.iter().map(|(_, spk)| spk)),
+        }
+    }
+
+    /// Derive any missing indices in `0..=up_to_index`. No-op for [`Self::Static`].
+    fn extend_to(&mut self, up_to_index: u32) {
+        if let Self::Descriptor {
+            descriptor, spks, ..
+        } = self
+        {
+            let next_index = spks.len() as u32;
+            for index in next_index..=up_to_index {
+                let spk = descriptor
+                    .at_derivation_index(index)
+                    .expect("descriptor index must be derivable")
+                    .script_pubkey();
+                spks.push((index, spk));
+            }
+        }
+    }
+
+    /// Record that `index` matched a block, and grow the inventory `gap_limit` indices past it.
+    ///
+    /// Returns whether new SPKs were derived as a result (always `false` for [`Self::Static`]).
+    fn mark_used(&mut self, index: u32) -> bool {
+        let Self::Descriptor {
+            gap_limit,
+            last_used,
+            spks,
+            ..
+        } = self
+        else {
+            return false;
+        };
+        if last_used.map(|last| index > last).unwrap_or(true) {
+            *last_used = Some(index);
+        }
+        let before = spks.len();
+        let up_to_index = index.saturating_add(*gap_limit);
+        self.extend_to(up_to_index);
+        match self {
+            Self::Descriptor { spks, .. } => spks.len() > before,
+            Self::Static(_) => false,
+        }
+    }
+}
 
 /// Type that returns Bitcoin blocks by matching a list of script pubkeys (SPKs) against a
 /// [`bip158::BlockFilter`].
 #[derive(Debug)]
-pub struct FilterIter<'a> {
-    /// RPC client
-    client: &'a bitcoincore_rpc::Client,
+pub struct FilterIter<'a, P> {
+    /// Filter/block data provider
+    provider: &'a P,
     /// SPK inventory
-    spks: Vec<ScriptBuf>,
+    inventory: SpkInventory,
     /// checkpoint
     cp: CheckPoint,
     /// Header info, contains the prev and next hashes for each header.
     header: Option<GetBlockHeaderResult>,
+    /// Whether to verify the filter header chain against `getblockfilterheader`.
+    verify_filter_headers: bool,
+    /// Filter header of the last block processed, used as the previous header when verifying
+    /// the next one. Seeded by [`Self::find_base`] from the actual base block's filter header
+    /// (or all-zero, per the BIP157 genesis case, only when the base height is 0).
+    prev_filter_header: FilterHeader,
+    /// Height of the last block that matched the SPK inventory, if any. Used to rewind and
+    /// re-scan forward whenever the descriptor inventory grows, since earlier blocks were only
+    /// checked against a smaller SPK set.
+    last_match_height: Option<u32>,
 }
 
-impl<'a> FilterIter<'a> {
-    /// Construct [`FilterIter`] with checkpoint, RPC client and SPKs.
+impl<'a, P: FilterProvider> FilterIter<'a, P> {
+    /// Construct [`FilterIter`] with checkpoint, provider and a fixed set of SPKs.
     pub fn new(
-        client: &'a bitcoincore_rpc::Client,
+        provider: &'a P,
         cp: CheckPoint,
         spks: impl IntoIterator<Item = ScriptBuf>,
     ) -> Self {
+        Self::new_with_inventory(provider, cp, SpkInventory::Static(spks.into_iter().collect()))
+    }
+
+    /// Construct [`FilterIter`] that derives its SPK inventory from `descriptor`, keeping it
+    /// `gap_limit` derivation indices ahead of the last index seen in a matching block.
+    ///
+    /// Once the scan reaches the tip with no new matches inside the gap window, the descriptor
+    /// has been fully discovered (up to the usual gap-limit caveat of non-sequential address
+    /// reuse).
+    pub fn new_with_descriptor(
+        provider: &'a P,
+        cp: CheckPoint,
+        descriptor: Descriptor<DescriptorPublicKey>,
+        gap_limit: u32,
+    ) -> Self {
+        let mut inventory = SpkInventory::Descriptor {
+            descriptor,
+            gap_limit,
+            spks: Vec::new(),
+            last_used: None,
+        };
+        inventory.extend_to(gap_limit.saturating_sub(1));
+        Self::new_with_inventory(provider, cp, inventory)
+    }
+
+    fn new_with_inventory(provider: &'a P, cp: CheckPoint, inventory: SpkInventory) -> Self {
         Self {
-            client,
-            spks: spks.into_iter().collect(),
+            provider,
+            inventory,
             cp,
             header: None,
+            verify_filter_headers: false,
+            prev_filter_header: FilterHeader::all_zeros(),
+            last_match_height: None,
         }
     }
 
-    /// Find the agreement height with the remote node and return the corresponding
-    /// header info.
+    /// Toggle verification of the BIP157 filter-header chain against `getblockfilterheader`.
+    ///
+    /// This catches a misconfigured or dishonest node serving filters that don't match the
+    /// committed cfheaders chain, at the cost of doubling the per-block RPC round trips, so it
+    /// is disabled by default.
+    pub fn verify_filter_headers(&mut self, verify: bool) {
+        self.verify_filter_headers = verify;
+    }
+
+    /// Find the agreement height with the remote node and return the corresponding header info,
+    /// together with the filter header of that base block (all-zero only if the base is
+    /// genesis), to seed [`Self::prev_filter_header`] when filter-header verification is on.
     ///
     /// Error if no agreement height is found.
-    fn find_base(&self) -> Result<GetBlockHeaderResult, Error> {
+    fn find_base(&self) -> Result<(GetBlockHeaderResult, FilterHeader), Error> {
         for cp in self.cp.iter() {
             let height = cp.height();
 
-            let fetched_hash = self.client.get_block_hash(height as u64)?;
+            let fetched_hash = self
+                .provider
+                .get_block_hash(height as u64)
+                .map_err(Into::into)?;
 
             if fetched_hash == cp.hash() {
-                return Ok(self.client.get_block_header_info(&fetched_hash)?);
+                let header = self
+                    .provider
+                    .get_block_header_info(&fetched_hash)
+                    .map_err(Into::into)?;
+                let base_filter_header = if self.verify_filter_headers && height > 0 {
+                    self.provider
+                        .get_block_filter_header(&fetched_hash)
+                        .map_err(Into::into)?
+                } else {
+                    FilterHeader::all_zeros()
+                };
+                return Ok((header, base_filter_header));
             }
         }
 
@@ -69,6 +274,9 @@ pub enum Event {
         cp: CheckPoint,
         /// block
         block: Block,
+        /// Derivation indices that matched this block, for a descriptor-based SPK inventory.
+        /// Empty when scanning a fixed, non-descriptor SPK set.
+        matched_indices: Vec<u32>,
     },
     /// No match
     NoMatch {
@@ -97,7 +305,7 @@ impl Event {
     }
 }
 
-impl Iterator for FilterIter<'_> {
+impl<P: FilterProvider> Iterator for FilterIter<'_, P> {
     type Item = Result<Event, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -109,9 +317,10 @@ impl Iterator for FilterIter<'_> {
                 None => {
                     // If no header is cached we need to locate a base of the local
                     // checkpoint from which the scan may proceed.
-                    let header = self.find_base()?;
+                    let (header, base_filter_header) = self.find_base()?;
                     let height: u32 = header.height.try_into()?;
                     cp = cp.range(..=height).next().expect("we found a base");
+                    self.prev_filter_header = base_filter_header;
 
                     header
                 }
@@ -122,7 +331,10 @@ impl Iterator for FilterIter<'_> {
                 None => return Ok(None),
             };
 
-            let mut next_header = self.client.get_block_header_info(&next_hash)?;
+            let mut next_header = self
+                .provider
+                .get_block_header_info(&next_hash)
+                .map_err(Into::into)?;
 
             // In case of a reorg, rewind by fetching headers of previous hashes until we find
             // one with enough confirmations.
@@ -131,7 +343,10 @@ impl Iterator for FilterIter<'_> {
                 let prev_hash = next_header
                     .previous_block_hash
                     .ok_or(Error::ReorgDepthExceeded)?;
-                let prev_header = self.client.get_block_header_info(&prev_hash)?;
+                let prev_header = self
+                    .provider
+                    .get_block_header_info(&prev_hash)
+                    .map_err(Into::into)?;
                 next_header = prev_header;
                 reorg_ct += 1;
             }
@@ -150,20 +365,99 @@ impl Iterator for FilterIter<'_> {
                 height: next_height,
                 hash: next_hash,
             };
-            let filter_bytes = self.client.get_block_filter(&next_hash)?.filter;
+            let filter_bytes = self
+                .provider
+                .get_block_filter(&next_hash)
+                .map_err(Into::into)?
+                .filter;
             let filter = BlockFilter::new(&filter_bytes);
 
+            if self.verify_filter_headers {
+                let filter_hash = FilterHeader::hash(&filter_bytes);
+                let mut preimage = Vec::with_capacity(64);
+                preimage.extend_from_slice(filter_hash.as_byte_array());
+                preimage.extend_from_slice(self.prev_filter_header.as_byte_array());
+                let computed_header = FilterHeader::hash(&preimage);
+
+                let expected_header = self
+                    .provider
+                    .get_block_filter_header(&next_hash)
+                    .map_err(Into::into)?;
+                if computed_header != expected_header {
+                    return Err(Error::FilterHeaderMismatch {
+                        height: next_height,
+                    });
+                }
+                self.prev_filter_header = computed_header;
+            }
+
+            let mut rewound = false;
             let next_event = if filter
-                .match_any(&next_hash, self.spks.iter().map(ScriptBuf::as_ref))
+                .match_any(&next_hash, self.inventory.scripts().map(ScriptBuf::as_ref))
                 .map_err(Error::Bip158)?
             {
-                let block = self.client.get_block(&next_hash)?;
-                cp = cp.insert(block_id);
+                // Figure out exactly which SPKs in the inventory matched, so a descriptor-based
+                // inventory can extend past the highest matching index and so the event can
+                // report it to the caller.
+                let matched_indices = match &self.inventory {
+                    SpkInventory::Static(_) => Vec::new(),
+                    SpkInventory::Descriptor { spks, .. } => {
+                        let mut matched = Vec::new();
+                        for (index, spk) in spks {
+                            if filter
+                                .match_any(&next_hash, core::iter::once(ScriptBuf::as_ref(spk)))
+                                .map_err(Error::Bip158)?
+                            {
+                                matched.push(*index);
+                            }
+                        }
+                        matched
+                    }
+                };
+
+                let grew = match matched_indices.iter().max() {
+                    Some(&highest) => self.inventory.mark_used(highest),
+                    None => false,
+                };
 
-                Ok(Some(Event::Block {
-                    cp: cp.clone(),
+                let block = self.provider.get_block(&next_hash).map_err(Into::into)?;
+                // The event reported to the caller always includes this matching block, even if
+                // `cp`/`self.cp` (the internal scan position) gets rewound below.
+                let event_cp = cp.clone().insert(block_id);
+                let event = Event::Block {
+                    cp: event_cp,
                     block,
-                }))
+                    matched_indices,
+                };
+
+                if grew {
+                    // A larger gap-limit window means earlier blocks may hold a match we
+                    // couldn't have seen with the smaller inventory. Rather than advancing `cp`
+                    // past this block, rewind it back to the last match (inclusive) so the
+                    // *next* call re-derives a base there and actually walks forward
+                    // re-checking the skipped blocks against the enlarged inventory. Note this
+                    // may re-emit events for blocks already returned once.
+                    let rewind_height = self.last_match_height.unwrap_or(0);
+                    cp = cp
+                        .range(..=rewind_height)
+                        .next()
+                        .ok_or(Error::ReorgDepthExceeded)?;
+                    if self.verify_filter_headers {
+                        self.prev_filter_header = if cp.height() == 0 {
+                            FilterHeader::all_zeros()
+                        } else {
+                            self.provider
+                                .get_block_filter_header(&cp.hash())
+                                .map_err(Into::into)?
+                        };
+                    }
+                    rewound = true;
+                } else {
+                    self.last_match_height = Some(next_height);
+                    cp = cp.insert(block_id);
+                }
+
+                Ok(Some(event))
             } else if next_header.next_block_hash.is_none() {
                 cp = cp.insert(block_id);
 
@@ -172,8 +466,9 @@ impl Iterator for FilterIter<'_> {
                 Ok(Some(Event::NoMatch { id: block_id }))
             };
 
-            // Store the next header
-            self.header = Some(next_header);
+            // Store the next header, unless we rewound the checkpoint above, in which case the
+            // next call should re-derive a base via `find_base` from the rewound checkpoint.
+            self.header = if rewound { None } else { Some(next_header) };
             // Update self.cp
             self.cp = cp;
 
@@ -194,6 +489,14 @@ pub enum Error {
     ReorgDepthExceeded,
     /// Error converting an integer
     TryFromInt(core::num::TryFromIntError),
+    /// The filter header computed from the filter at `height` didn't match the header returned
+    /// by `getblockfilterheader`.
+    FilterHeaderMismatch {
+        /// Height of the block whose filter header failed to verify.
+        height: u32,
+    },
+    /// The provider returned a filter header that isn't a well-formed 32-byte hash.
+    InvalidFilterHeader,
 }
 
 impl core::fmt::Display for Error {
@@ -203,6 +506,10 @@ impl core::fmt::Display for Error {
             Self::Bip158(e) => write!(f, "{e}"),
             Self::ReorgDepthExceeded => write!(f, "maximum reorg depth exceeded"),
             Self::TryFromInt(e) => write!(f, "{e}"),
+            Self::FilterHeaderMismatch { height } => {
+                write!(f, "filter header mismatch at height {height}")
+            }
+            Self::InvalidFilterHeader => write!(f, "provider returned a malformed filter header"),
         }
     }
 }
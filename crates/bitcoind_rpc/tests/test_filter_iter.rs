@@ -1,8 +1,49 @@
-use bdk_bitcoind_rpc::bip158::{Error, FilterIter};
+use bdk_bitcoind_rpc::bip158::{Error, FilterHeader, FilterIter, FilterProvider};
 use bdk_core::{BlockId, CheckPoint};
 use bdk_testenv::{anyhow, bitcoind, TestEnv};
-use bitcoin::{Address, Amount, Network, ScriptBuf};
+use bitcoin::{Address, Amount, Block, BlockHash, Network, ScriptBuf};
+use bitcoincore_rpc::json::{GetBlockFilterResult, GetBlockHeaderResult};
 use bitcoincore_rpc::RpcApi;
+use miniscript::Descriptor;
+
+/// The well-known BIP32 test vector 1 master extended key, reused here purely as a stable
+/// descriptor fixture (no funds are ever derived from its real key material in mainnet contexts).
+const TEST_TPRV: &str = "tprv8ZgxMBicQKsPeDgjzdC36fs6bMjGApWDNLR9erAXMcrw9EZTqC8jjT2JJXvyPGgfHauVXrFWkkr4w2vZ2tTLKqBVgZYAWi8YZ3PeVrgkhBm";
+
+/// A [`FilterProvider`] that forwards everything to `inner` except `get_block_filter_header`,
+/// which always returns a header that doesn't match what the filter actually commits to. Used to
+/// exercise [`FilterIter`]'s `FilterHeaderMismatch` detection without needing a real
+/// misbehaving node.
+struct BadFilterHeaderProvider<'a> {
+    inner: &'a bitcoincore_rpc::Client,
+}
+
+impl FilterProvider for BadFilterHeaderProvider<'_> {
+    type Error = Error;
+
+    fn get_block_hash(&self, height: u64) -> Result<BlockHash, Self::Error> {
+        FilterProvider::get_block_hash(self.inner, height)
+    }
+
+    fn get_block_header_info(&self, hash: &BlockHash) -> Result<GetBlockHeaderResult, Self::Error> {
+        FilterProvider::get_block_header_info(self.inner, hash)
+    }
+
+    fn get_block_filter(&self, hash: &BlockHash) -> Result<GetBlockFilterResult, Self::Error> {
+        FilterProvider::get_block_filter(self.inner, hash)
+    }
+
+    fn get_block_filter_header(&self, _hash: &BlockHash) -> Result<FilterHeader, Self::Error> {
+        use bitcoin::hashes::Hash;
+        Ok(bitcoin::hashes::sha256d::Hash::hash(
+            b"not the real filter header",
+        ))
+    }
+
+    fn get_block(&self, hash: &BlockHash) -> Result<Block, Self::Error> {
+        FilterProvider::get_block(self.inner, hash)
+    }
+}
 
 fn testenv() -> anyhow::Result<TestEnv> {
     let mut conf = bitcoind::Conf::default();
@@ -112,3 +153,88 @@ fn filter_iter_detects_reorgs() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn filter_iter_descriptor_gap_limit_triggers_rescan() -> anyhow::Result<()> {
+    let env = testenv()?;
+    let (descriptor, _) = Descriptor::parse_descriptor(
+        &bitcoin::secp256k1::Secp256k1::new(),
+        &format!("wpkh({TEST_TPRV}/84'/1'/0'/0/*)"),
+    )?;
+
+    // With `gap_limit = 1` the inventory starts out covering only derivation index 0, so a match
+    // at index 0 immediately grows the window and should force a rewind-and-rescan rather than
+    // silently skipping the blocks in between.
+    let spk0 = descriptor.at_derivation_index(0)?.script_pubkey();
+    let addr0 = Address::from_script(&spk0, Network::Regtest)?;
+
+    // Mine past coinbase maturity so the node's wallet has funds to send.
+    let _ = env.mine_blocks(101, None)?;
+    let _txid = env.send(&addr0, Amount::from_btc(0.1)?)?;
+    let _ = env.mine_blocks(5, None)?;
+
+    let genesis_hash = env.genesis_hash()?;
+    let cp = CheckPoint::new(BlockId {
+        height: 0,
+        hash: genesis_hash,
+    });
+
+    let iter = FilterIter::new_with_descriptor(&env.bitcoind.client, cp, descriptor, 1);
+
+    let mut matched_heights = Vec::new();
+    let mut saw_tip = false;
+    for res in iter {
+        let event = res?;
+        if event.is_match() {
+            matched_heights.push(event.height());
+        }
+        if matches!(event, bdk_bitcoind_rpc::bip158::Event::Tip { .. }) {
+            saw_tip = true;
+        }
+    }
+
+    assert!(saw_tip, "scan should reach the tip without erroring");
+    // The matching block is rescanned once after the gap-limit window grows to cover it, so its
+    // height is reported twice rather than once.
+    let match_height = matched_heights
+        .first()
+        .copied()
+        .expect("the funded block should match");
+    assert_eq!(
+        matched_heights.iter().filter(|&&h| h == match_height).count(),
+        2,
+        "expected the matching height to be rescanned after the gap-limit window grew"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn filter_iter_detects_bad_filter_header() -> anyhow::Result<()> {
+    let env = testenv()?;
+    let _ = env.mine_blocks(5, None)?;
+
+    let genesis_hash = env.genesis_hash()?;
+    let cp = CheckPoint::new(BlockId {
+        height: 0,
+        hash: genesis_hash,
+    });
+
+    let provider = BadFilterHeaderProvider {
+        inner: &env.bitcoind.client,
+    };
+    let mut iter = FilterIter::new(&provider, cp, [ScriptBuf::new()]);
+    iter.verify_filter_headers(true);
+
+    let mut saw_mismatch = false;
+    for res in iter {
+        if let Err(Error::FilterHeaderMismatch { .. }) = res {
+            saw_mismatch = true;
+            break;
+        }
+        res?;
+    }
+    assert!(saw_mismatch, "a lying provider should trip FilterHeaderMismatch");
+
+    Ok(())
+}
@@ -27,12 +27,90 @@ impl Balance {
         self.confirmed + self.trusted_pending
     }
 
+    /// Get sum of `trusted_pending` and the `finalized` portion of `by_depth`, excluding any
+    /// confirmed coins that have not yet reached the finality threshold `by_depth` was computed
+    /// with.
+    ///
+    /// This is a finality-aware variant of [`trusted_spendable`](Self::trusted_spendable); see
+    /// [`BalanceByDepth`] for why a caller might want it.
+    pub fn trusted_spendable_finalized(&self, by_depth: &BalanceByDepth) -> Amount {
+        self.trusted_pending + by_depth.finalized
+    }
+
     /// Get the whole balance visible to the wallet.
     pub fn total(&self) -> Amount {
         self.confirmed + self.trusted_pending + self.untrusted_pending + self.immature
     }
 }
 
+/// A confirmed coin's value together with the height at which it confirmed.
+///
+/// Used as input to [`BalanceByDepth::new`] since splitting [`Balance::confirmed`] by finality
+/// depth requires per-UTXO confirmation heights rather than a single pre-summed amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmedUtxo {
+    /// Value of the coin.
+    pub value: Amount,
+    /// Height at which the coin confirmed.
+    pub confirmation_height: u32,
+}
+
+/// [`Balance::confirmed`] split by confirmation depth relative to a caller-chosen finality
+/// threshold.
+///
+/// Some applications (for example swap or exchange integrations) track a configurable
+/// `min_confirmations` and only treat coins as settled once they reach that depth, which is
+/// coarser than the wallet's own `confirmed >= 1` notion. `BalanceByDepth` separates `confirmed`
+/// coins into `finalized` (confirmation count >= `min_confirmations`) and `confirming`
+/// (1..min_confirmations) so callers can make that distinction without re-walking the UTXO set
+/// themselves.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct BalanceByDepth {
+    /// Sum of confirmed coins that have reached `min_confirmations` depth.
+    pub finalized: Amount,
+    /// Sum of confirmed coins with depth in `1..min_confirmations`.
+    pub confirming: Amount,
+}
+
+impl BalanceByDepth {
+    /// Split `utxos` into `finalized` and `confirming` given the current chain `tip_height` and
+    /// `min_confirmations`.
+    ///
+    /// A coin's confirmation depth is `tip_height - confirmation_height + 1`. UTXOs whose
+    /// `confirmation_height` is greater than `tip_height` (i.e. not actually confirmed yet) are
+    /// ignored.
+    pub fn new(
+        utxos: impl IntoIterator<Item = ConfirmedUtxo>,
+        tip_height: u32,
+        min_confirmations: u32,
+    ) -> Self {
+        let mut finalized = Amount::ZERO;
+        let mut confirming = Amount::ZERO;
+        for utxo in utxos {
+            if utxo.confirmation_height > tip_height {
+                continue;
+            }
+            let depth = tip_height - utxo.confirmation_height + 1;
+            if depth >= min_confirmations {
+                finalized += utxo.value;
+            } else {
+                confirming += utxo.value;
+            }
+        }
+        Self {
+            finalized,
+            confirming,
+        }
+    }
+
+    /// Sum of `finalized` and `confirming`.
+    ///
+    /// This should equal the [`Balance::confirmed`] the UTXOs were drawn from.
+    pub fn confirmed(&self) -> Amount {
+        self.finalized + self.confirming
+    }
+}
+
 impl core::fmt::Display for Balance {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
@@ -55,3 +133,36 @@ impl core::ops::Add for Balance {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utxo(value: u64, confirmation_height: u32) -> ConfirmedUtxo {
+        ConfirmedUtxo {
+            value: Amount::from_sat(value),
+            confirmation_height,
+        }
+    }
+
+    #[test]
+    fn by_depth_skips_utxos_not_yet_confirmed_at_tip() {
+        // A UTXO whose confirmation height is above the tip hasn't actually confirmed from the
+        // perspective of `tip_height` (e.g. a stale read), so it must be ignored entirely rather
+        // than counted as `confirming`.
+        let by_depth = BalanceByDepth::new([utxo(1_000, 11)], 10, 6);
+        assert_eq!(by_depth.finalized, Amount::ZERO);
+        assert_eq!(by_depth.confirming, Amount::ZERO);
+    }
+
+    #[test]
+    fn by_depth_boundary_at_min_confirmations() {
+        // depth = tip_height - confirmation_height + 1; at tip 10 and min_confirmations 6, a coin
+        // confirming at height 5 has depth 6 (exactly the threshold) and must land in
+        // `finalized`, while one at height 6 has depth 5 and must land in `confirming`.
+        let by_depth = BalanceByDepth::new([utxo(1_000, 5), utxo(2_000, 6)], 10, 6);
+        assert_eq!(by_depth.finalized, Amount::from_sat(1_000));
+        assert_eq!(by_depth.confirming, Amount::from_sat(2_000));
+        assert_eq!(by_depth.confirmed(), Amount::from_sat(3_000));
+    }
+}
@@ -0,0 +1,288 @@
+//! A minimal 3072-bit unsigned integer type used by [`super::UtxoSetHash`], reduced modulo the
+//! prime `p = 2^3072 - 1103717` chosen for the MuHash3072 construction.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// Number of 32-bit limbs needed to hold a 3072-bit value.
+const LIMBS: usize = 96;
+/// `c` such that the MuHash3072 modulus is `p = 2^3072 - c`.
+const MODULUS_C: u32 = 1_103_717;
+
+/// An element of the field `Z/pZ`, represented as `LIMBS` little-endian 32-bit limbs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Num3072 {
+    limbs: [u32; LIMBS],
+}
+
+impl Num3072 {
+    /// The multiplicative identity.
+    pub(super) fn one() -> Self {
+        let mut limbs = [0u32; LIMBS];
+        limbs[0] = 1;
+        Self { limbs }
+    }
+
+    /// Expand a 32-byte seed into a 3072-bit field element via a ChaCha20 keystream.
+    ///
+    /// The seed is used as the ChaCha20 key (with an all-zero nonce), and the first `LIMBS`
+    /// 32-bit words of the keystream become the limbs of the result, reduced modulo `p` if the
+    /// raw keystream happens to land on or above the modulus (astronomically unlikely, but
+    /// cheap to handle).
+    pub(super) fn from_seed(seed: [u8; 32]) -> Self {
+        let mut key = [0u32; 8];
+        for (word, chunk) in key.iter_mut().zip(seed.chunks_exact(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().expect("chunk of 4 bytes"));
+        }
+        let nonce = [0u32; 3];
+
+        let mut limbs = [0u32; LIMBS];
+        for (block_idx, chunk) in limbs.chunks_exact_mut(16).enumerate() {
+            chunk.copy_from_slice(&chacha20_block(&key, &nonce, block_idx as u32));
+        }
+
+        let mut num = Self { limbs };
+        num.reduce_if_ge_modulus();
+        num
+    }
+
+    /// Serialize to 384 little-endian bytes.
+    pub(super) fn to_bytes(self) -> [u8; LIMBS * 4] {
+        let mut out = [0u8; LIMBS * 4];
+        for (chunk, limb) in out.chunks_exact_mut(4).zip(self.limbs.iter()) {
+            chunk.copy_from_slice(&limb.to_le_bytes());
+        }
+        out
+    }
+
+    /// Multiply two field elements modulo `p`.
+    pub(super) fn mul(&self, other: &Self) -> Self {
+        // Schoolbook multiplication into a wide little-endian limb vector, then fold the
+        // 6144-bit product back down to 3072 bits using `2^3072 ≡ MODULUS_C (mod p)`.
+        let mut wide = vec![0u64; LIMBS * 2];
+        for (i, &a) in self.limbs.iter().enumerate() {
+            if a == 0 {
+                continue;
+            }
+            let mut carry: u64 = 0;
+            for (j, &b) in other.limbs.iter().enumerate() {
+                let idx = i + j;
+                let sum = (a as u64) * (b as u64) + wide[idx] + carry;
+                wide[idx] = sum & 0xFFFF_FFFF;
+                carry = sum >> 32;
+            }
+            let mut k = i + LIMBS;
+            while carry != 0 {
+                let sum = wide[k] + carry;
+                wide[k] = sum & 0xFFFF_FFFF;
+                carry = sum >> 32;
+                k += 1;
+            }
+        }
+
+        let mut num = Self {
+            limbs: reduce_limbs(wide),
+        };
+        num.reduce_if_ge_modulus();
+        num
+    }
+
+    /// Compute the modular inverse `self^-1 mod p` via Fermat's little theorem.
+    ///
+    /// `p` is prime (that's precisely why `c = 1103717` was chosen for MuHash3072), so
+    /// `self^(p - 2) mod p` is the inverse for any nonzero `self`.
+    pub(super) fn modinv(&self) -> Self {
+        self.pow(&Self::p_minus_two())
+    }
+
+    /// Square-and-multiply exponentiation modulo `p`.
+    fn pow(&self, exponent: &[u32; LIMBS]) -> Self {
+        let mut result = Self::one();
+        for &limb in exponent.iter().rev() {
+            for bit in (0..32).rev() {
+                result = result.mul(&result);
+                if (limb >> bit) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        result
+    }
+
+    /// `p - 2`, as a little-endian limb array, for use as a Fermat exponent.
+    ///
+    /// `p - 2 = (2^3072 - 1) - (MODULUS_C + 1)`, i.e. all bits set, minus `MODULUS_C + 1`.
+    fn p_minus_two() -> [u32; LIMBS] {
+        let mut limbs = [u32::MAX; LIMBS];
+        let mut borrow = MODULUS_C as u64 + 1;
+        for limb in limbs.iter_mut() {
+            if borrow == 0 {
+                break;
+            }
+            let (diff, new_borrow) = if (*limb as u64) >= borrow {
+                ((*limb as u64 - borrow) as u32, 0)
+            } else {
+                (((*limb as u64) + (1u64 << 32) - borrow) as u32, 1)
+            };
+            *limb = diff;
+            borrow = new_borrow;
+        }
+        debug_assert_eq!(borrow, 0, "MODULUS_C + 1 must fit in the top limb's borrow chain");
+        limbs
+    }
+
+    /// If `self >= p`, reduce it to `self - p`.
+    ///
+    /// `value >= p` iff `value + MODULUS_C` overflows 3072 bits, since `p = 2^3072 -
+    /// MODULUS_C`. In that case the overflowed sum, truncated back to `LIMBS` limbs, is exactly
+    /// `value - p`.
+    fn reduce_if_ge_modulus(&mut self) {
+        let mut carry = MODULUS_C as u64;
+        let mut reduced = [0u32; LIMBS];
+        for (out, &limb) in reduced.iter_mut().zip(self.limbs.iter()) {
+            let sum = limb as u64 + carry;
+            *out = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry != 0 {
+            self.limbs = reduced;
+        }
+    }
+}
+
+/// Fold a little-endian limb vector of arbitrary length down to exactly [`LIMBS`] limbs, using
+/// the identity `2^3072 ≡ MODULUS_C (mod p)`: the limbs at or beyond index `LIMBS` (the "high"
+/// part, weighted by `2^(3072 + 32*i)`) are scaled by `MODULUS_C` and added back into the low
+/// `LIMBS` limbs, repeating until everything fits back in `LIMBS` limbs.
+fn reduce_limbs(mut limbs: Vec<u64>) -> [u32; LIMBS] {
+    while limbs.len() > LIMBS {
+        let high = limbs.split_off(LIMBS);
+
+        // `high * MODULUS_C`, as a little-endian limb vector.
+        let mut scaled = Vec::with_capacity(high.len() + 1);
+        let mut carry: u64 = 0;
+        for h in high {
+            let product = h * MODULUS_C as u64 + carry;
+            scaled.push(product & 0xFFFF_FFFF);
+            carry = product >> 32;
+        }
+        while carry != 0 {
+            scaled.push(carry & 0xFFFF_FFFF);
+            carry >>= 32;
+        }
+        let scaled_len = scaled.len();
+
+        // `limbs += scaled`. Any carry out of the addition must keep rippling through the
+        // *existing* low limbs starting at `scaled_len`, not jump straight to the end of
+        // `limbs` — that's what made the previous version of this fold lose carries whenever
+        // `scaled` was shorter than `limbs`.
+        let mut carry: u64 = 0;
+        for (i, s) in scaled.into_iter().enumerate() {
+            let existing = limbs.get(i).copied().unwrap_or(0);
+            let total = existing + s + carry;
+            if i < limbs.len() {
+                limbs[i] = total & 0xFFFF_FFFF;
+            } else {
+                limbs.push(total & 0xFFFF_FFFF);
+            }
+            carry = total >> 32;
+        }
+        let mut i = scaled_len;
+        while carry != 0 {
+            let existing = limbs.get(i).copied().unwrap_or(0);
+            let total = existing + carry;
+            if i < limbs.len() {
+                limbs[i] = total & 0xFFFF_FFFF;
+            } else {
+                limbs.push(total & 0xFFFF_FFFF);
+            }
+            carry = total >> 32;
+            i += 1;
+        }
+    }
+    let mut out = [0u32; LIMBS];
+    for (out_limb, limb) in out.iter_mut().zip(limbs) {
+        *out_limb = limb as u32;
+    }
+    out
+}
+
+/// The ChaCha20 constants `"expand 32-byte k"`, as little-endian words.
+const CHACHA20_CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+/// One 64-byte ChaCha20 keystream block (RFC 8439), as 16 little-endian words.
+fn chacha20_block(key: &[u32; 8], nonce: &[u32; 3], counter: u32) -> [u32; 16] {
+    let mut state = [0u32; 16];
+    state[0..4].copy_from_slice(&CHACHA20_CONSTANTS);
+    state[4..12].copy_from_slice(key);
+    state[12] = counter;
+    state[13..16].copy_from_slice(nonce);
+
+    let mut working = state;
+    for _ in 0..10 {
+        quarter_round(&mut working, 0, 4, 8, 12);
+        quarter_round(&mut working, 1, 5, 9, 13);
+        quarter_round(&mut working, 2, 6, 10, 14);
+        quarter_round(&mut working, 3, 7, 11, 15);
+        quarter_round(&mut working, 0, 5, 10, 15);
+        quarter_round(&mut working, 1, 6, 11, 12);
+        quarter_round(&mut working, 2, 7, 8, 13);
+        quarter_round(&mut working, 3, 4, 9, 14);
+    }
+    for (w, s) in working.iter_mut().zip(state.iter()) {
+        *w = w.wrapping_add(*s);
+    }
+    working
+}
+
+fn quarter_round(s: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] ^= s[a];
+    s[d] = s[d].rotate_left(16);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] ^= s[c];
+    s[b] = s[b].rotate_left(12);
+    s[a] = s[a].wrapping_add(s[b]);
+    s[d] ^= s[a];
+    s[d] = s[d].rotate_left(8);
+    s[c] = s[c].wrapping_add(s[d]);
+    s[b] ^= s[c];
+    s[b] = s[b].rotate_left(7);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn one_is_multiplicative_identity() {
+        let seeded = Num3072::from_seed([7u8; 32]);
+        assert_eq!(seeded.mul(&Num3072::one()), seeded);
+    }
+
+    #[test]
+    fn modinv_round_trips_to_one() {
+        let seeded = Num3072::from_seed([3u8; 32]);
+        assert_eq!(seeded.mul(&seeded.modinv()), Num3072::one());
+    }
+
+    #[test]
+    fn mul_is_commutative() {
+        let a = Num3072::from_seed([1u8; 32]);
+        let b = Num3072::from_seed([2u8; 32]);
+        assert_eq!(a.mul(&b), b.mul(&a));
+    }
+
+    #[test]
+    fn from_seed_round_trips_through_bytes() {
+        let num = Num3072::from_seed([9u8; 32]);
+        let bytes = num.to_bytes();
+        // The encoding is the little-endian limbs of the reduced field element, so re-parsing
+        // every 4-byte chunk must give back every limb.
+        let reparsed: Vec<u32> = bytes
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(reparsed, num.limbs);
+    }
+}